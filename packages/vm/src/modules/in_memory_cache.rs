@@ -1,11 +1,21 @@
 use clru::{CLruCache, CLruCacheConfig, WeightScale};
 use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
 use std::num::NonZeroUsize;
-use wasmer::Module;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use wasmer::{Engine, Module};
 
 use super::cached_module::CachedModule;
 use crate::{Checksum, Size, VmError, VmResult};
 
+/// Number of shards used by [`ConcurrentInMemoryCache`].
+///
+/// Chosen as a power of two so the shard index can be derived from the
+/// checksum's first byte with a cheap bitmask.
+const CONCURRENT_CACHE_SHARDS: usize = 16;
+
 // Minimum module size.
 // Based on `examples/module_size.sh`, and the cosmwasm-plus contracts.
 // We use an estimated *minimum* module size in order to compute a number of pre-allocated entries
@@ -25,15 +35,221 @@ impl WeightScale<Checksum, CachedModule> for SizeScale {
     }
 }
 
+/// Strategy used to determine a stored module's weight against the cache's
+/// size budget.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SizeWeighting {
+    /// Trust the caller-supplied `size` passed to `store`, typically a crude
+    /// multiple of the wasm byte length.
+    Estimated,
+    /// Ignore the caller-supplied `size` and instead weigh the module by the
+    /// size of its serialized compilation artifact (`Module::serialize`),
+    /// which tracks actual resident memory far more closely than
+    /// `wasm.len() * factor`. Chains that need a precise memory cap should
+    /// opt into this.
+    Measured,
+}
+
+/// A snapshot of cache usage, useful for right-sizing the cache's memory
+/// budget per deployment.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Metrics {
+    /// Number of `load` calls that found the requested module.
+    pub hits: u64,
+    /// Number of `load` calls that did not find the requested module.
+    pub misses: u64,
+    /// Number of `store` calls.
+    pub stores: u64,
+    /// Number of entries evicted from the LRU to make room for new ones.
+    pub evictions: u64,
+    /// Current number of elements in the cache (see [`InMemoryCache::len`]).
+    pub len: usize,
+    /// Current cumulative size of all elements in the cache (see
+    /// [`InMemoryCache::size`]).
+    pub size: usize,
+}
+
+/// Models the gas/weight cost of materializing a module on a cache miss, as
+/// a flat base cost plus a coefficient applied to the module's stored size.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LoadCostModel {
+    /// Flat cost charged regardless of module size.
+    pub base_cost: u64,
+    /// Additional cost charged per byte of the module's stored size.
+    pub per_byte_cost: u64,
+}
+
+impl LoadCostModel {
+    pub const fn new(base_cost: u64, per_byte_cost: u64) -> Self {
+        LoadCostModel {
+            base_cost,
+            per_byte_cost,
+        }
+    }
+
+    fn cost_for(&self, size: usize) -> u64 {
+        self.base_cost
+            .saturating_add(self.per_byte_cost.saturating_mul(size as u64))
+    }
+}
+
+impl Default for LoadCostModel {
+    /// No load cost is charged by default; callers that want to meter cache
+    /// misses must opt in via [`InMemoryCache::new_with_load_cost_model`].
+    fn default() -> Self {
+        LoadCostModel {
+            base_cost: 0,
+            per_byte_cost: 0,
+        }
+    }
+}
+
+/// A disk-backed second tier for modules evicted from the in-memory LRU.
+///
+/// Rather than dropping an evicted module's compiled artifact outright, it
+/// is serialized to a file keyed by checksum so a later cache miss can
+/// deserialize it instead of recompiling from wasm. The engine used for
+/// deserialization is captured at construction time, since it never changes
+/// over the lifetime of a cache and this keeps it out of `load`'s signature.
+///
+/// Retention: a spilled file is removed as soon as it is successfully
+/// reloaded into memory, since at that point the in-memory LRU is the
+/// authoritative copy again and the on-disk artifact would otherwise
+/// accumulate forever. A file is left on disk only between an eviction and
+/// either its next reload or the cache being dropped; there is currently no
+/// background GC for artifacts that are spilled and never reloaded again.
+struct DiskFallback {
+    base_dir: PathBuf,
+    engine: Engine,
+}
+
+impl DiskFallback {
+    /// Files are namespaced by the wasmer version that produced them. This
+    /// is belt-and-suspenders on top of `Module::deserialize`'s own header
+    /// check: it avoids even attempting to deserialize (and logging/failing
+    /// on) an artifact we already know was written by an incompatible
+    /// wasmer build, e.g. after a node binary upgrade.
+    fn path_for(&self, checksum: &Checksum) -> PathBuf {
+        self.base_dir
+            .join(format!("{}-{}", wasmer::VERSION, checksum.to_hex()))
+    }
+
+    /// Serializes and persists an evicted module, prefixed with its
+    /// original cache `size` so a later reload can restore the exact same
+    /// weight rather than re-deriving it from the serialized artifact
+    /// (which would silently diverge from the caller's estimate under
+    /// `SizeWeighting::Estimated`). Errors are swallowed: a failure to
+    /// spill to disk must not prevent the eviction itself, it only means
+    /// the module will need to be recompiled on the next miss.
+    fn spill(&self, checksum: &Checksum, module: &Module, size: usize) {
+        let Ok(artifact) = module.serialize() else {
+            return;
+        };
+        let mut bytes = Vec::with_capacity(8 + artifact.len());
+        bytes.extend_from_slice(&(size as u64).to_le_bytes());
+        bytes.extend_from_slice(&artifact);
+
+        if std::fs::create_dir_all(&self.base_dir).is_ok() {
+            let _ = std::fs::write(self.path_for(checksum), bytes);
+        }
+    }
+
+    /// Attempts to load a previously spilled module together with its
+    /// original cache size, validating the artifact against the engine
+    /// captured at construction the same way any other deserialized
+    /// artifact is validated, so a stale artifact from an incompatible
+    /// wasmer build is rejected rather than causing undefined behaviour.
+    ///
+    /// On a successful reload, the spilled file is deleted: the module is
+    /// resident in memory again and the on-disk copy would otherwise never
+    /// be cleaned up.
+    fn load(&self, checksum: &Checksum) -> Option<(Module, usize)> {
+        let path = self.path_for(checksum);
+        let bytes = std::fs::read(&path).ok()?;
+        if bytes.len() < 8 {
+            return None;
+        }
+        let (size_bytes, artifact) = bytes.split_at(8);
+        let size = u64::from_le_bytes(size_bytes.try_into().ok()?) as usize;
+        // SAFETY: `Module::deserialize` validates the artifact's header
+        // (including the wasmer version) before treating the remaining
+        // bytes as compiled code, rejecting anything that was not produced
+        // by a compatible `Module::serialize`.
+        let module = unsafe { Module::deserialize(&self.engine, artifact.to_vec()) }.ok()?;
+        let _ = std::fs::remove_file(&path);
+        Some((module, size))
+    }
+}
+
 /// An in-memory module cache
 pub struct InMemoryCache {
     modules: Option<CLruCache<Checksum, CachedModule, RandomState, SizeScale>>,
+    /// Modules that are pinned in memory and therefore exempt from the LRU
+    /// eviction used for `modules`. Looked up before `modules` in `load`.
+    pinned_modules: HashMap<Checksum, CachedModule>,
+    weighting: SizeWeighting,
+    /// Optional on-disk second tier that evicted modules are spilled to.
+    disk_fallback: Option<DiskFallback>,
+    load_cost_model: LoadCostModel,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    stores: AtomicU64,
+    evictions: AtomicU64,
 }
 
 impl InMemoryCache {
     /// Creates a new cache with the given size (in bytes)
     /// and pre-allocated entries.
+    ///
+    /// Weighs stored modules using the caller-supplied size estimate. Use
+    /// [`InMemoryCache::new_with_weighting`] to weigh by measured artifact
+    /// size instead.
     pub fn new(size: Size) -> Self {
+        Self::new_with_weighting(size, SizeWeighting::Estimated)
+    }
+
+    /// Creates a new cache with the given size (in bytes) and pre-allocated
+    /// entries, using the given strategy to weigh stored modules against
+    /// the size budget.
+    pub fn new_with_weighting(size: Size, weighting: SizeWeighting) -> Self {
+        Self::new_with_options(size, weighting, None, LoadCostModel::default())
+    }
+
+    /// Creates a new cache that, on eviction, spills modules to `base_dir`
+    /// instead of dropping their compiled artifact, so a later miss can
+    /// deserialize them rather than recompiling from wasm. `engine` is used
+    /// to validate and deserialize spilled artifacts. The pure in-memory
+    /// behaviour remains the default; this must be opted into.
+    pub fn new_with_disk_fallback(
+        size: Size,
+        weighting: SizeWeighting,
+        base_dir: PathBuf,
+        engine: Engine,
+    ) -> Self {
+        Self::new_with_options(
+            size,
+            weighting,
+            Some(DiskFallback { base_dir, engine }),
+            LoadCostModel::default(),
+        )
+    }
+
+    /// Creates a new cache that charges `load_cost_model` against a
+    /// checksum's stored size, for callers that meter gas on cache misses.
+    pub fn new_with_load_cost_model(
+        size: Size,
+        weighting: SizeWeighting,
+        load_cost_model: LoadCostModel,
+    ) -> Self {
+        Self::new_with_options(size, weighting, None, load_cost_model)
+    }
+
+    fn new_with_options(
+        size: Size,
+        weighting: SizeWeighting,
+        disk_fallback: Option<DiskFallback>,
+        load_cost_model: LoadCostModel,
+    ) -> Self {
         let preallocated_entries = size.0 / MINIMUM_MODULE_SIZE.0;
 
         InMemoryCache {
@@ -46,10 +262,89 @@ impl InMemoryCache {
             } else {
                 None
             },
+            pinned_modules: HashMap::new(),
+            weighting,
+            disk_fallback,
+            load_cost_model,
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+            stores: AtomicU64::new(0),
+            evictions: AtomicU64::new(0),
+        }
+    }
+
+    /// Returns the estimated gas/weight cost of materializing the given
+    /// module, based on its stored size and the cache's [`LoadCostModel`].
+    ///
+    /// Returns `None` if the module is not currently cached (in memory or
+    /// pinned), since its size is not known without loading it.
+    ///
+    /// Callers can use this to charge more gas when a contract has to be
+    /// pulled from a cold cache than when it is served from a warm hit,
+    /// preventing an attacker from amortizing the instantiation cost of a
+    /// very large module across many cheap calls.
+    pub fn load_cost(&self, checksum: &Checksum) -> Option<u64> {
+        let size = if let Some(cached) = self.pinned_modules.get(checksum) {
+            cached.size
+        } else {
+            self.modules.as_ref()?.peek(checksum)?.size
+        };
+        Some(self.load_cost_model.cost_for(size))
+    }
+
+    /// Evicts entries from the LRU (spilling each to the disk fallback tier
+    /// if configured) until `incoming_size` fits under the weight budget.
+    /// Shared by `store`, `unpin` and the disk-hit path of `load` so that an
+    /// insertion from any of these callers accounts for evictions the same way.
+    fn make_room_for(&mut self, incoming_size: usize) {
+        if let Some(modules) = &mut self.modules {
+            while modules.len() > 0 && modules.weight() + incoming_size > modules.cap().get() {
+                let Some((evicted_checksum, evicted)) = modules.pop_lru() else {
+                    break;
+                };
+                if let Some(disk_fallback) = &self.disk_fallback {
+                    disk_fallback.spill(&evicted_checksum, &evicted.module, evicted.size);
+                }
+                self.evictions.fetch_add(1, Ordering::Relaxed);
+            }
         }
     }
 
     pub fn store(&mut self, checksum: &Checksum, entry: Module, size: usize) -> VmResult<()> {
+        let size = match self.weighting {
+            SizeWeighting::Estimated => size,
+            // Fall back to the estimate if the module cannot be serialized;
+            // this should not happen for a module that compiled successfully,
+            // but we must not fail the store because of it.
+            SizeWeighting::Measured => entry
+                .serialize()
+                .map(|bytes| bytes.len())
+                .unwrap_or(size),
+        };
+
+        self.stores.fetch_add(1, Ordering::Relaxed);
+
+        // A pinned module lives only in `pinned_modules`, never in the LRU.
+        // Update it in place rather than also inserting into the LRU, which
+        // would leave two copies around (one returned by `load`, the other
+        // silently evictable) and double-count towards `len`/`size`.
+        if self.pinned_modules.contains_key(checksum) {
+            self.pinned_modules.insert(
+                *checksum,
+                CachedModule {
+                    module: entry,
+                    size,
+                },
+            );
+            return Ok(());
+        }
+
+        // Make room for the new entry ourselves (rather than letting
+        // `put_with_weight` evict internally) so that, when a disk fallback
+        // is configured, we get a chance to spill each evicted module
+        // before its compiled artifact is dropped.
+        self.make_room_for(size);
+
         if let Some(modules) = &mut self.modules {
             modules
                 .put_with_weight(
@@ -66,33 +361,247 @@ impl InMemoryCache {
 
     /// Looks up a module in the cache and creates a new module
     pub fn load(&mut self, checksum: &Checksum) -> VmResult<Option<CachedModule>> {
-        if let Some(modules) = &mut self.modules {
-            match modules.get(checksum) {
-                Some(cached) => Ok(Some(cached.clone())),
-                None => Ok(None),
+        if let Some(cached) = self.pinned_modules.get(checksum) {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            return Ok(Some(cached.clone()));
+        }
+
+        let found = if let Some(modules) = &mut self.modules {
+            modules.get(checksum).cloned()
+        } else {
+            None
+        };
+
+        let found = if found.is_some() {
+            found
+        } else {
+            // Resolve the disk hit (if any) before touching `self.modules`
+            // mutably below, since the module itself owns the data we need
+            // and doesn't keep a borrow of `self.disk_fallback` alive.
+            let disk_hit = self.disk_fallback.as_ref().and_then(|df| df.load(checksum));
+            match disk_hit {
+                Some((module, size)) => {
+                    let cached = CachedModule { module, size };
+                    // Route through the same eviction/spill accounting as
+                    // `store`, so a reload from disk can't silently evict
+                    // another module without spilling it in turn.
+                    self.make_room_for(size);
+                    if let Some(modules) = &mut self.modules {
+                        let _ = modules.put_with_weight(*checksum, cached.clone());
+                    }
+                    Some(cached)
+                }
+                None => None,
             }
+        };
+
+        if found.is_some() {
+            self.hits.fetch_add(1, Ordering::Relaxed);
         } else {
-            Ok(None)
+            self.misses.fetch_add(1, Ordering::Relaxed);
+        }
+        Ok(found)
+    }
+
+    /// Looks up a module like `load`, additionally returning the estimated
+    /// [`LoadCostModel`] cost for its size. This avoids a caller needing a
+    /// second, separately-racing call to `load_cost` after the fact.
+    pub fn load_with_cost(&mut self, checksum: &Checksum) -> VmResult<Option<(CachedModule, u64)>> {
+        let found = self.load(checksum)?;
+        Ok(found.map(|cached| {
+            let cost = self.load_cost_model.cost_for(cached.size);
+            (cached, cost)
+        }))
+    }
+
+    /// Returns a snapshot of cache hit/miss/store/eviction counters, along
+    /// with the current `len`/`size`.
+    pub fn metrics(&self) -> Metrics {
+        Metrics {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            stores: self.stores.load(Ordering::Relaxed),
+            evictions: self.evictions.load(Ordering::Relaxed),
+            len: self.len(),
+            size: self.size(),
+        }
+    }
+
+    /// Pins a module, moving it from the regular LRU cache (if present there)
+    /// into a dedicated map that is never subject to weighted eviction.
+    ///
+    /// This is meant for a small number of hot system/governance contracts
+    /// that a validator wants to guarantee stay resident regardless of churn
+    /// from other contracts passing through `store`.
+    ///
+    /// Returns an error if the module is not currently cached.
+    pub fn pin(&mut self, checksum: &Checksum) -> VmResult<()> {
+        if self.pinned_modules.contains_key(checksum) {
+            return Ok(());
+        }
+
+        let cached = self
+            .modules
+            .as_mut()
+            .and_then(|modules| modules.pop(checksum))
+            .ok_or_else(|| VmError::cache_err("Module to pin was not found in cache"))?;
+
+        self.pinned_modules.insert(*checksum, cached);
+        Ok(())
+    }
+
+    /// Unpins a module, moving it back into the regular LRU cache where it
+    /// becomes subject to weighted eviction again.
+    ///
+    /// This is a no-op if the module is not currently pinned.
+    pub fn unpin(&mut self, checksum: &Checksum) -> VmResult<()> {
+        if let Some(cached) = self.pinned_modules.remove(checksum) {
+            // Make room ourselves (rather than letting `put_with_weight`
+            // evict internally) so that an unpin forcing an eviction spills
+            // the evicted module to disk and counts towards `evictions`,
+            // the same as any other insertion.
+            self.make_room_for(cached.size);
+            if let Some(modules) = &mut self.modules {
+                modules
+                    .put_with_weight(*checksum, cached)
+                    .map_err(|e| VmError::cache_err(format!("{e:?}")))?;
+            }
         }
+        Ok(())
     }
 
-    /// Returns the number of elements in the cache.
+    /// Returns the number of elements in the cache, including pinned modules.
     pub fn len(&self) -> usize {
-        self.modules
+        let unpinned = self
+            .modules
             .as_ref()
             .map(|modules| modules.len())
-            .unwrap_or_default()
+            .unwrap_or_default();
+        unpinned + self.pinned_modules.len()
     }
 
-    /// Returns cumulative size of all elements in the cache.
+    /// Returns cumulative size of all elements in the cache, including
+    /// pinned modules.
     ///
     /// This is based on the values provided with `store`. No actual
     /// memory size is measured here.
     pub fn size(&self) -> usize {
-        self.modules
+        let unpinned = self
+            .modules
             .as_ref()
             .map(|modules| modules.weight())
-            .unwrap_or_default()
+            .unwrap_or_default();
+        unpinned + self.pinned_size()
+    }
+
+    /// Returns the cumulative size of all pinned modules.
+    ///
+    /// Pinned modules do not count against the weighted LRU budget used by
+    /// `SizeScale`; this returns their total on its own, while `size`
+    /// includes it.
+    pub fn pinned_size(&self) -> usize {
+        self.pinned_modules.values().map(|cached| cached.size).sum()
+    }
+}
+
+/// A sharded, thread-safe variant of [`InMemoryCache`].
+///
+/// `InMemoryCache` requires `&mut self` for every operation, which forces a
+/// global lock around module lookups even though `load` is logically
+/// read-mostly. This cache splits the checksum space into
+/// [`CONCURRENT_CACHE_SHARDS`] independent LRUs, each behind its own mutex,
+/// so that worker threads executing different contracts only contend when
+/// their checksums happen to land in the same shard. Eviction remains
+/// weighted per shard, with the configured `Size` budget split evenly
+/// across shards.
+pub struct ConcurrentInMemoryCache {
+    /// `None` for a shard whose budget rounded down to zero bytes, mirroring
+    /// `InMemoryCache::new(Size(0))`'s silent no-op behaviour rather than
+    /// giving the shard a tiny cap that would make every `store` into it
+    /// fail with an error.
+    shards: Vec<Option<Mutex<CLruCache<Checksum, CachedModule, RandomState, SizeScale>>>>,
+}
+
+impl ConcurrentInMemoryCache {
+    /// Creates a new cache with the given total size (in bytes), divided
+    /// evenly across shards.
+    pub fn new(size: Size) -> Self {
+        let shard_size = Size(size.0 / CONCURRENT_CACHE_SHARDS);
+        let preallocated_entries = shard_size.0 / MINIMUM_MODULE_SIZE.0;
+
+        let shards = (0..CONCURRENT_CACHE_SHARDS)
+            .map(|_| {
+                if shard_size.0 > 0 {
+                    Some(Mutex::new(CLruCache::with_config(
+                        CLruCacheConfig::new(NonZeroUsize::new(shard_size.0).unwrap())
+                            .with_memory(preallocated_entries)
+                            .with_scale(SizeScale),
+                    )))
+                } else {
+                    None
+                }
+            })
+            .collect();
+
+        ConcurrentInMemoryCache { shards }
+    }
+
+    /// Picks the shard responsible for a given checksum, based on its first
+    /// byte. `CONCURRENT_CACHE_SHARDS` is a power of two, so this is a mask
+    /// rather than a modulo.
+    fn shard_for(
+        &self,
+        checksum: &Checksum,
+    ) -> &Option<Mutex<CLruCache<Checksum, CachedModule, RandomState, SizeScale>>> {
+        let index = checksum.as_slice()[0] as usize & (CONCURRENT_CACHE_SHARDS - 1);
+        &self.shards[index]
+    }
+
+    pub fn store(&self, checksum: &Checksum, entry: Module, size: usize) -> VmResult<()> {
+        if let Some(shard) = self.shard_for(checksum) {
+            shard
+                .lock()
+                .unwrap()
+                .put_with_weight(
+                    *checksum,
+                    CachedModule {
+                        module: entry,
+                        size,
+                    },
+                )
+                .map_err(|e| VmError::cache_err(format!("{e:?}")))?;
+        }
+        Ok(())
+    }
+
+    /// Looks up a module in the cache and creates a new module
+    pub fn load(&self, checksum: &Checksum) -> VmResult<Option<CachedModule>> {
+        Ok(self
+            .shard_for(checksum)
+            .as_ref()
+            .and_then(|shard| shard.lock().unwrap().get(checksum).cloned()))
+    }
+
+    /// Returns the number of elements in the cache, summed across shards.
+    pub fn len(&self) -> usize {
+        self.shards
+            .iter()
+            .flatten()
+            .map(|shard| shard.lock().unwrap().len())
+            .sum()
+    }
+
+    /// Returns cumulative size of all elements in the cache, summed across
+    /// shards.
+    ///
+    /// This is based on the values provided with `store`. No actual
+    /// memory size is measured here.
+    pub fn size(&self) -> usize {
+        self.shards
+            .iter()
+            .flatten()
+            .map(|shard| shard.lock().unwrap().weight())
+            .sum()
     }
 }
 
@@ -139,12 +648,13 @@ mod tests {
         .unwrap();
         let checksum = Checksum::generate(&wasm);
 
+        // Compile module
+        let engine = make_compiling_engine(TESTING_MEMORY_LIMIT);
+
         // Module does not exist
         let cache_entry = cache.load(&checksum).unwrap();
         assert!(cache_entry.is_none());
 
-        // Compile module
-        let engine = make_compiling_engine(TESTING_MEMORY_LIMIT);
         let original = Module::new(&engine, &wasm).unwrap();
 
         // Ensure original module can be executed
@@ -294,4 +804,425 @@ mod tests {
         cache.store(&checksum3, module, 1_500_000).unwrap();
         assert_eq!(cache.size(), 1_500_000);
     }
+
+    #[test]
+    fn metrics_tracks_hits_misses_stores_and_evictions() {
+        let mut cache = InMemoryCache::new(Size::mebi(2));
+
+        let wasm1 = wat::parse_str(
+            r#"(module
+            (type $t0 (func (param i32) (result i32)))
+            (func $add_one (export "add_one") (type $t0) (param $p0 i32) (result i32)
+                get_local $p0
+                i32.const 1
+                i32.add)
+            )"#,
+        )
+        .unwrap();
+        let checksum1 = Checksum::generate(&wasm1);
+        let wasm2 = wat::parse_str(
+            r#"(module
+            (type $t0 (func (param i32) (result i32)))
+            (func $add_one (export "add_two") (type $t0) (param $p0 i32) (result i32)
+                get_local $p0
+                i32.const 2
+                i32.add)
+            )"#,
+        )
+        .unwrap();
+        let checksum2 = Checksum::generate(&wasm2);
+        let engine1 = make_compiling_engine(TESTING_MEMORY_LIMIT);
+
+        // Miss
+        assert!(cache.load(&checksum1).unwrap().is_none());
+
+        // Store 1 (no eviction, cache was empty)
+        let module1 = Module::new(&engine1, &wasm1).unwrap();
+        cache.store(&checksum1, module1, 1_500_000).unwrap();
+
+        // Hit
+        assert!(cache.load(&checksum1).unwrap().is_some());
+
+        // Store 2 (evicts 1, since the budget only fits one of these)
+        let engine2 = make_compiling_engine(TESTING_MEMORY_LIMIT);
+        let module2 = Module::new(&engine2, &wasm2).unwrap();
+        cache.store(&checksum2, module2, 1_500_000).unwrap();
+
+        let metrics = cache.metrics();
+        assert_eq!(metrics.hits, 1);
+        assert_eq!(metrics.misses, 1);
+        assert_eq!(metrics.stores, 2);
+        assert_eq!(metrics.evictions, 1);
+        assert_eq!(metrics.len, 1);
+        assert_eq!(metrics.size, 1_500_000);
+    }
+
+    #[test]
+    fn measured_weighting_ignores_caller_supplied_size() {
+        let mut cache = InMemoryCache::new_with_weighting(Size::mebi(2), SizeWeighting::Measured);
+
+        let wasm = wat::parse_str(
+            r#"(module
+            (type $t0 (func (param i32) (result i32)))
+            (func $add_one (export "add_one") (type $t0) (param $p0 i32) (result i32)
+                get_local $p0
+                i32.const 1
+                i32.add)
+            )"#,
+        )
+        .unwrap();
+        let checksum = Checksum::generate(&wasm);
+
+        let engine = make_compiling_engine(TESTING_MEMORY_LIMIT);
+        let module = Module::new(&engine, &wasm).unwrap();
+        let serialized_size = module.serialize().unwrap().len();
+
+        // The bogus estimate below is ignored in favour of the measured
+        // serialized artifact size.
+        cache.store(&checksum, module, 1).unwrap();
+        assert_eq!(cache.size(), serialized_size);
+    }
+
+    #[test]
+    fn load_cost_reflects_stored_size() {
+        let mut cache = InMemoryCache::new_with_load_cost_model(
+            Size::mebi(2),
+            SizeWeighting::Estimated,
+            LoadCostModel::new(1_000, 2),
+        );
+
+        let wasm = wat::parse_str(
+            r#"(module
+            (type $t0 (func (param i32) (result i32)))
+            (func $add_one (export "add_one") (type $t0) (param $p0 i32) (result i32)
+                get_local $p0
+                i32.const 1
+                i32.add)
+            )"#,
+        )
+        .unwrap();
+        let checksum = Checksum::generate(&wasm);
+
+        // Unknown checksum has no known size to charge for
+        assert_eq!(cache.load_cost(&checksum), None);
+
+        let engine = make_compiling_engine(TESTING_MEMORY_LIMIT);
+        let module = Module::new(&engine, &wasm).unwrap();
+        cache.store(&checksum, module, 900_000).unwrap();
+
+        assert_eq!(cache.load_cost(&checksum), Some(1_000 + 2 * 900_000));
+    }
+
+    #[test]
+    fn load_with_cost_returns_module_and_cost_together() {
+        let mut cache = InMemoryCache::new_with_load_cost_model(
+            Size::mebi(2),
+            SizeWeighting::Estimated,
+            LoadCostModel::new(1_000, 2),
+        );
+
+        let wasm = wat::parse_str(
+            r#"(module
+            (type $t0 (func (param i32) (result i32)))
+            (func $add_one (export "add_one") (type $t0) (param $p0 i32) (result i32)
+                get_local $p0
+                i32.const 1
+                i32.add)
+            )"#,
+        )
+        .unwrap();
+        let checksum = Checksum::generate(&wasm);
+
+        // Unknown checksum is a miss, not an error
+        assert!(cache.load_with_cost(&checksum).unwrap().is_none());
+
+        let engine = make_compiling_engine(TESTING_MEMORY_LIMIT);
+        let module = Module::new(&engine, &wasm).unwrap();
+        cache.store(&checksum, module, 900_000).unwrap();
+
+        let (cached, cost) = cache.load_with_cost(&checksum).unwrap().unwrap();
+        assert_eq!(cached.size, 900_000);
+        assert_eq!(cost, 1_000 + 2 * 900_000);
+    }
+
+    #[test]
+    fn disk_fallback_round_trips_an_evicted_module() {
+        let base_dir = std::env::temp_dir().join("cosmwasm-vm-in-memory-cache-test-disk-fallback");
+        let _ = std::fs::remove_dir_all(&base_dir);
+
+        let engine = make_compiling_engine(TESTING_MEMORY_LIMIT);
+        let mut cache = InMemoryCache::new_with_disk_fallback(
+            Size::mebi(2),
+            SizeWeighting::Estimated,
+            base_dir.clone(),
+            engine.clone(),
+        );
+
+        let wasm1 = wat::parse_str(
+            r#"(module
+            (type $t0 (func (param i32) (result i32)))
+            (func $add_one (export "add_one") (type $t0) (param $p0 i32) (result i32)
+                get_local $p0
+                i32.const 1
+                i32.add)
+            )"#,
+        )
+        .unwrap();
+        let checksum1 = Checksum::generate(&wasm1);
+        let wasm2 = wat::parse_str(
+            r#"(module
+            (type $t0 (func (param i32) (result i32)))
+            (func $add_one (export "add_two") (type $t0) (param $p0 i32) (result i32)
+                get_local $p0
+                i32.const 2
+                i32.add)
+            )"#,
+        )
+        .unwrap();
+        let checksum2 = Checksum::generate(&wasm2);
+
+        let module1 = Module::new(&engine, &wasm1).unwrap();
+        cache.store(&checksum1, module1, 1_500_000).unwrap();
+
+        // Pushes checksum1 out of the in-memory LRU, spilling it to disk.
+        let module2 = Module::new(&engine, &wasm2).unwrap();
+        cache.store(&checksum2, module2, 1_500_000).unwrap();
+        assert_eq!(cache.metrics().evictions, 1);
+
+        // checksum1 is gone from the LRU, but recoverable from disk, with its
+        // original size preserved rather than re-derived from the serialized
+        // artifact.
+        let recovered = cache.load(&checksum1).unwrap();
+        assert_eq!(recovered.map(|cached| cached.size), Some(1_500_000));
+
+        // The spilled file is cleaned up once reloaded, so it doesn't
+        // accumulate on disk forever.
+        let spilled_path = base_dir.join(format!("{}-{}", wasmer::VERSION, checksum1.to_hex()));
+        assert!(!spilled_path.exists());
+
+        let _ = std::fs::remove_dir_all(&base_dir);
+    }
+
+    #[test]
+    fn unpin_forced_eviction_spills_to_disk() {
+        let base_dir = std::env::temp_dir().join("cosmwasm-vm-in-memory-cache-test-unpin-spill");
+        let _ = std::fs::remove_dir_all(&base_dir);
+
+        let engine = make_compiling_engine(TESTING_MEMORY_LIMIT);
+        let mut cache = InMemoryCache::new_with_disk_fallback(
+            Size::mebi(2),
+            SizeWeighting::Estimated,
+            base_dir.clone(),
+            engine.clone(),
+        );
+
+        let wasm1 = wat::parse_str(
+            r#"(module
+            (type $t0 (func (param i32) (result i32)))
+            (func $add_one (export "add_one") (type $t0) (param $p0 i32) (result i32)
+                get_local $p0
+                i32.const 1
+                i32.add)
+            )"#,
+        )
+        .unwrap();
+        let checksum1 = Checksum::generate(&wasm1);
+        let wasm2 = wat::parse_str(
+            r#"(module
+            (type $t0 (func (param i32) (result i32)))
+            (func $add_one (export "add_two") (type $t0) (param $p0 i32) (result i32)
+                get_local $p0
+                i32.const 2
+                i32.add)
+            )"#,
+        )
+        .unwrap();
+        let checksum2 = Checksum::generate(&wasm2);
+
+        let module1 = Module::new(&engine, &wasm1).unwrap();
+        cache.store(&checksum1, module1, 900_000).unwrap();
+        cache.pin(&checksum1).unwrap();
+
+        // checksum2 alone fits comfortably in the unpinned budget.
+        let module2 = Module::new(&engine, &wasm2).unwrap();
+        cache.store(&checksum2, module2, 1_500_000).unwrap();
+        assert_eq!(cache.metrics().evictions, 0);
+
+        // Unpinning checksum1 forces it back into an LRU that is already
+        // carrying checksum2, evicting checksum2 to stay under budget. That
+        // eviction must go through the same spill-and-count path as any
+        // other, not silently drop the module via a bare `put_with_weight`.
+        cache.unpin(&checksum1).unwrap();
+        assert_eq!(cache.metrics().evictions, 1);
+
+        let spilled_path = base_dir.join(format!("{}-{}", wasmer::VERSION, checksum2.to_hex()));
+        assert!(spilled_path.exists());
+
+        let _ = std::fs::remove_dir_all(&base_dir);
+    }
+
+    #[test]
+    fn pin_unpin_works() {
+        let mut cache = InMemoryCache::new(Size::mebi(2));
+
+        let wasm1 = wat::parse_str(
+            r#"(module
+            (type $t0 (func (param i32) (result i32)))
+            (func $add_one (export "add_one") (type $t0) (param $p0 i32) (result i32)
+                get_local $p0
+                i32.const 1
+                i32.add)
+            )"#,
+        )
+        .unwrap();
+        let checksum1 = Checksum::generate(&wasm1);
+        let wasm2 = wat::parse_str(
+            r#"(module
+            (type $t0 (func (param i32) (result i32)))
+            (func $add_one (export "add_two") (type $t0) (param $p0 i32) (result i32)
+                get_local $p0
+                i32.const 2
+                i32.add)
+            )"#,
+        )
+        .unwrap();
+        let checksum2 = Checksum::generate(&wasm2);
+
+        // Pinning an unknown checksum fails
+        assert!(cache.pin(&checksum1).is_err());
+
+        let engine1 = make_compiling_engine(TESTING_MEMORY_LIMIT);
+        let module1 = Module::new(&engine1, &wasm1).unwrap();
+        cache.store(&checksum1, module1, 900_000).unwrap();
+
+        // Pin checksum1; it no longer counts against the LRU budget
+        cache.pin(&checksum1).unwrap();
+        assert_eq!(cache.len(), 1);
+        assert_eq!(cache.size(), 900_000);
+        assert_eq!(cache.pinned_size(), 900_000);
+
+        // A burst of other modules cannot evict a pinned module
+        let engine2 = make_compiling_engine(TESTING_MEMORY_LIMIT);
+        let module2 = Module::new(&engine2, &wasm2).unwrap();
+        cache.store(&checksum2, module2, 1_500_000).unwrap();
+        assert!(cache.load(&checksum1).unwrap().is_some());
+        assert!(cache.load(&checksum2).unwrap().is_some());
+
+        // Unpinning returns the module to the regular LRU cache. checksum2
+        // leaves too little of the budget for checksum1, so reinserting it forces
+        // an eviction of checksum2 - which must be counted the same as any
+        // other eviction caused by `store`.
+        cache.unpin(&checksum1).unwrap();
+        assert_eq!(cache.pinned_size(), 0);
+        assert!(cache.load(&checksum1).unwrap().is_some());
+        assert_eq!(cache.metrics().evictions, 1);
+
+        // Unpinning a checksum that is not pinned is a no-op
+        cache.unpin(&checksum1).unwrap();
+    }
+
+    #[test]
+    fn store_updates_a_pinned_module_in_place() {
+        let mut cache = InMemoryCache::new(Size::mebi(2));
+
+        let wasm = wat::parse_str(
+            r#"(module
+            (type $t0 (func (param i32) (result i32)))
+            (func $add_one (export "add_one") (type $t0) (param $p0 i32) (result i32)
+                get_local $p0
+                i32.const 1
+                i32.add)
+            )"#,
+        )
+        .unwrap();
+        let checksum = Checksum::generate(&wasm);
+
+        let engine = make_compiling_engine(TESTING_MEMORY_LIMIT);
+        let module = Module::new(&engine, &wasm).unwrap();
+        cache.store(&checksum, module, 900_000).unwrap();
+        cache.pin(&checksum).unwrap();
+
+        // Storing again for an already-pinned checksum must not create a
+        // second, separately-evictable copy in the LRU.
+        let module_again = Module::new(&engine, &wasm).unwrap();
+        cache.store(&checksum, module_again, 950_000).unwrap();
+
+        assert_eq!(cache.len(), 1);
+        assert_eq!(cache.size(), 950_000);
+        assert_eq!(cache.pinned_size(), 950_000);
+    }
+
+    #[test]
+    fn concurrent_cache_store_and_load_works() {
+        // 16 MiB split over 16 shards leaves 1 MiB per shard, enough for
+        // the 900_000-byte entry below.
+        let cache = ConcurrentInMemoryCache::new(Size::mebi(16));
+
+        let wasm = wat::parse_str(
+            r#"(module
+            (type $t0 (func (param i32) (result i32)))
+            (func $add_one (export "add_one") (type $t0) (param $p0 i32) (result i32)
+                get_local $p0
+                i32.const 1
+                i32.add)
+            )"#,
+        )
+        .unwrap();
+        let checksum = Checksum::generate(&wasm);
+
+        assert!(cache.load(&checksum).unwrap().is_none());
+
+        let engine = make_compiling_engine(TESTING_MEMORY_LIMIT);
+        let module = Module::new(&engine, &wasm).unwrap();
+        cache.store(&checksum, module, 900_000).unwrap();
+
+        let cached = cache.load(&checksum).unwrap().unwrap();
+        let mut store = Store::new(engine);
+        let instance = WasmerInstance::new(&mut store, &cached.module, &imports! {}).unwrap();
+        set_remaining_points(&mut store, &instance, TESTING_GAS_LIMIT);
+        let add_one = instance.exports.get_function("add_one").unwrap();
+        let result = add_one.call(&mut store, &[42.into()]).unwrap();
+        assert_eq!(result[0].unwrap_i32(), 43);
+
+        assert_eq!(cache.len(), 1);
+        assert_eq!(cache.size(), 900_000);
+    }
+
+    #[test]
+    fn concurrent_cache_is_actually_concurrent() {
+        use std::sync::Arc;
+        use std::thread;
+
+        let cache = Arc::new(ConcurrentInMemoryCache::new(Size::mebi(16)));
+        let engine = make_compiling_engine(TESTING_MEMORY_LIMIT);
+
+        let handles: Vec<_> = (0..8u8)
+            .map(|i| {
+                let cache = Arc::clone(&cache);
+                let engine = engine.clone();
+                thread::spawn(move || {
+                    let wasm = wat::parse_str(format!(
+                        r#"(module
+                        (type $t0 (func (param i32) (result i32)))
+                        (func $add_one (export "add_one") (type $t0) (param $p0 i32) (result i32)
+                            get_local $p0
+                            i32.const {i}
+                            i32.add)
+                        )"#
+                    ))
+                    .unwrap();
+                    let checksum = Checksum::generate(&wasm);
+                    let module = Module::new(&engine, &wasm).unwrap();
+                    cache.store(&checksum, module, 100_000).unwrap();
+                    assert!(cache.load(&checksum).unwrap().is_some());
+                })
+            })
+            .collect();
+
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        assert_eq!(cache.len(), 8);
+    }
 }